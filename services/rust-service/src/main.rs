@@ -14,11 +14,16 @@ use opentelemetry::{
 };
 use std::time::Instant as StdInstant;
 use opentelemetry_sdk::{
-    metrics::SdkMeterProvider,
+    logs::LoggerProvider,
+    metrics::{
+        reader::{DefaultAggregationSelector, DefaultTemporalitySelector},
+        PeriodicReader, SdkMeterProvider,
+    },
     propagation::TraceContextPropagator,
     trace::{self, RandomIdGenerator, Sampler},
     Resource,
 };
+use opentelemetry_appender_tracing::layer::OpenTelemetryTracingBridge;
 use std::sync::OnceLock;
 use std::time::Duration;
 use opentelemetry_otlp::WithExportConfig;
@@ -29,11 +34,16 @@ use tower_http::trace::TraceLayer;
 use tracing::{info, error};
 use tracing_subscriber::layer::SubscriberExt;
 use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::Layer;
 
 // Global metrics instruments
 static REQUEST_COUNTER: OnceLock<Counter<u64>> = OnceLock::new();
 static DURATION_HISTOGRAM: OnceLock<Histogram<f64>> = OnceLock::new();
 
+// Prometheus registry backing the `/metrics` scrape endpoint (present only
+// when the Prometheus pull reader is enabled).
+static PROMETHEUS_REGISTRY: OnceLock<prometheus::Registry> = OnceLock::new();
+
 async fn root_handler() -> Json<serde_json::Value> {
     info!("Processing root request");
     
@@ -106,6 +116,30 @@ async fn health_handler() -> Json<serde_json::Value> {
     }))
 }
 
+// Prometheus scrape endpoint: encode the registry in the text exposition
+// format expected by a plain Prometheus server.
+async fn metrics_handler() -> Response {
+    use prometheus::{Encoder, TextEncoder};
+
+    let Some(registry) = PROMETHEUS_REGISTRY.get() else {
+        return (StatusCode::NOT_FOUND, "Prometheus exporter not enabled").into_response();
+    };
+
+    let encoder = TextEncoder::new();
+    let mut buffer = Vec::new();
+    if let Err(e) = encoder.encode(&registry.gather(), &mut buffer) {
+        error!("Failed to encode Prometheus metrics: {}", e);
+        return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+    }
+
+    (
+        StatusCode::OK,
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        buffer,
+    )
+        .into_response()
+}
+
 // Middleware to track HTTP metrics
 async fn metrics_middleware(
     req: Request,
@@ -120,8 +154,7 @@ async fn metrics_middleware(
     
     // Record metrics
     let duration = start.elapsed();
-    let status = response.status().as_u16();
-    
+
     // Use pre-initialized metrics instruments
     if let Some(counter) = REQUEST_COUNTER.get() {
         counter.add(
@@ -146,70 +179,301 @@ async fn metrics_middleware(
     response
 }
 
-fn init_telemetry() -> Result<SdkMeterProvider, Box<dyn std::error::Error>> {
+// Resolve the OTLP protocol for a signal, honouring the signal-specific
+// override (`OTEL_EXPORTER_OTLP_{TRACES,METRICS,LOGS}_PROTOCOL`) before falling
+// back to the general `OTEL_EXPORTER_OTLP_PROTOCOL`. Recognised values are
+// `grpc` (default) and `http/protobuf`.
+fn otlp_protocol(signal_var: &str) -> String {
+    std::env::var(signal_var)
+        .or_else(|_| std::env::var("OTEL_EXPORTER_OTLP_PROTOCOL"))
+        .unwrap_or_else(|_| "grpc".to_string())
+}
+
+// Parse `OTEL_EXPORTER_OTLP_HEADERS` (comma-separated `key=value` pairs,
+// typically an auth token header) into a map shared by both the gRPC and HTTP
+// exporter builders.
+fn otlp_headers() -> std::collections::HashMap<String, String> {
+    let mut headers = std::collections::HashMap::new();
+    if let Ok(raw) = std::env::var("OTEL_EXPORTER_OTLP_HEADERS") {
+        for pair in raw.split(',') {
+            if let Some((key, value)) = pair.split_once('=') {
+                headers.insert(key.trim().to_string(), value.trim().to_string());
+            }
+        }
+    }
+    headers
+}
+
+// Build a tonic (gRPC) OTLP exporter for `endpoint`, applying any custom
+// headers as gRPC metadata and enabling TLS with the system root certificates
+// when the endpoint is `https://`. This is what lets the service export to
+// authenticated hosted backends rather than only a plaintext local collector.
+fn tonic_exporter(endpoint: &str) -> Result<opentelemetry_otlp::TonicExporterBuilder, Box<dyn std::error::Error>> {
+    let mut builder = opentelemetry_otlp::new_exporter()
+        .tonic()
+        .with_endpoint(endpoint.to_string());
+
+    let headers = otlp_headers();
+    if !headers.is_empty() {
+        let mut metadata = tonic::metadata::MetadataMap::new();
+        for (key, value) in headers {
+            let key = tonic::metadata::MetadataKey::from_bytes(key.as_bytes())?;
+            metadata.insert(key, value.parse()?);
+        }
+        builder = builder.with_metadata(metadata);
+    }
+
+    if endpoint.starts_with("https://") {
+        // The `tls-roots` feature makes `ClientTlsConfig` load the system root
+        // certificates, which is what hosted backends require.
+        builder = builder.with_tls_config(tonic::transport::ClientTlsConfig::new());
+    }
+
+    Ok(builder)
+}
+
+// Build an HTTP/protobuf OTLP exporter for `endpoint`, applying the same
+// custom headers as the gRPC path so authenticated backends (Honeycomb,
+// Lightstep) work over OTLP/HTTP too. TLS is handled by the HTTP client
+// whenever the endpoint is `https://`.
+fn http_exporter(endpoint: String) -> opentelemetry_otlp::HttpExporterBuilder {
+    let mut builder = opentelemetry_otlp::new_exporter()
+        .http()
+        .with_endpoint(endpoint);
+
+    let headers = otlp_headers();
+    if !headers.is_empty() {
+        builder = builder.with_headers(headers);
+    }
+
+    builder
+}
+
+// Build an OTLP log exporter pipeline for the selected protocol, mirroring
+// the trace/metric endpoint handling so every signal shares one endpoint.
+fn logger_provider(
+    protocol: &str,
+    grpc_endpoint: &str,
+    http_endpoint: &str,
+    resource: Resource,
+) -> Result<LoggerProvider, Box<dyn std::error::Error>> {
+    let config = opentelemetry_sdk::logs::Config::default().with_resource(resource);
+    let provider = if protocol == "http/protobuf" {
+        opentelemetry_otlp::new_pipeline()
+            .logging()
+            .with_log_config(config)
+            .with_exporter(http_exporter(format!(
+                "{}/v1/logs",
+                http_endpoint.trim_end_matches('/')
+            )))
+            .install_batch(opentelemetry_sdk::runtime::Tokio)?
+    } else {
+        opentelemetry_otlp::new_pipeline()
+            .logging()
+            .with_log_config(config)
+            .with_exporter(tonic_exporter(grpc_endpoint)?)
+            .install_batch(opentelemetry_sdk::runtime::Tokio)?
+    };
+    Ok(provider)
+}
+
+// Resolve the head-based sampler from `OTEL_TRACES_SAMPLER` /
+// `OTEL_TRACES_SAMPLER_ARG`, defaulting to `parentbased_traceidratio` with a
+// ratio of 1.0. The parent-based variants honour the sampling decision
+// propagated from upstream services (via the `TraceContextPropagator`) and
+// only apply the ratio to unsampled roots, keeping distributed traces
+// consistent end to end.
+fn trace_sampler() -> Sampler {
+    let ratio = std::env::var("OTEL_TRACES_SAMPLER_ARG")
+        .ok()
+        .and_then(|v| v.parse::<f64>().ok())
+        .unwrap_or(1.0);
+
+    match std::env::var("OTEL_TRACES_SAMPLER")
+        .unwrap_or_else(|_| "parentbased_traceidratio".to_string())
+        .as_str()
+    {
+        "always_on" => Sampler::AlwaysOn,
+        "always_off" => Sampler::AlwaysOff,
+        "traceidratio" => Sampler::TraceIdRatioBased(ratio),
+        _ => Sampler::ParentBased(Box::new(Sampler::TraceIdRatioBased(ratio))),
+    }
+}
+
+fn init_telemetry(
+) -> Result<(SdkMeterProvider, LoggerProvider, Option<prometheus::Registry>), Box<dyn std::error::Error>> {
     // Get OTLP endpoint from environment - using gRPC port 4317
     let otlp_endpoint = std::env::var("OTEL_EXPORTER_OTLP_ENDPOINT")
         .unwrap_or_else(|_| "http://otel-collector:4317".to_string());
-    
+    // HTTP/protobuf listens on a separate port (4318). Derive it from the
+    // configured gRPC endpoint by swapping the default port so a single
+    // `OTEL_EXPORTER_OTLP_ENDPOINT` works for both transports; an explicit
+    // HTTP endpoint can still be set via `OTEL_EXPORTER_OTLP_HTTP_ENDPOINT`.
+    let otlp_http_endpoint = std::env::var("OTEL_EXPORTER_OTLP_HTTP_ENDPOINT")
+        .unwrap_or_else(|_| otlp_endpoint.replace(":4317", ":4318"));
+
     info!("Initializing OpenTelemetry with endpoint: {}", otlp_endpoint);
-    
-    // Create resource with service information
+
+    // Create resource with service information. Service identity can be
+    // overridden from the environment, and every signal additionally carries
+    // the host name and a process-lifetime instance id so individual replicas
+    // are distinguishable in multi-replica deployments.
+    let service_name = std::env::var("OTEL_SERVICE_NAME")
+        .unwrap_or_else(|_| "rust-service".to_string());
+    let service_version = std::env::var("OTEL_SERVICE_VERSION")
+        .unwrap_or_else(|_| "1.0.0".to_string());
     let resource = Resource::new(vec![
-        KeyValue::new(semconv::resource::SERVICE_NAME, "rust-service"),
-        KeyValue::new(semconv::resource::SERVICE_VERSION, "1.0.0"),
+        KeyValue::new(semconv::resource::SERVICE_NAME, service_name),
+        KeyValue::new(semconv::resource::SERVICE_VERSION, service_version),
+        KeyValue::new(
+            semconv::resource::HOST_NAME,
+            gethostname::gethostname().to_string_lossy().into_owned(),
+        ),
+        KeyValue::new(
+            semconv::resource::SERVICE_INSTANCE_ID,
+            uuid::Uuid::new_v4().to_string(),
+        ),
     ]);
-    
+
     // Set up trace context propagation
     global::set_text_map_propagator(TraceContextPropagator::new());
-    
-    // Set up tracing with gRPC exporter
-    let tracer = opentelemetry_otlp::new_pipeline()
-        .tracing()
-        .with_exporter(
-            opentelemetry_otlp::new_exporter()
-                .tonic()
-                .with_endpoint(otlp_endpoint.clone()),
-        )
-        .with_trace_config(
-            trace::config()
-                .with_sampler(Sampler::AlwaysOn)
-                .with_id_generator(RandomIdGenerator::default())
-                .with_resource(resource.clone()),
-        )
-        .install_batch(opentelemetry_sdk::runtime::Tokio)?;
-    
-    // Set up metrics with gRPC exporter
-    let meter_provider = opentelemetry_otlp::new_pipeline()
-        .metrics(opentelemetry_sdk::runtime::Tokio)
-        .with_exporter(
-            opentelemetry_otlp::new_exporter()
-                .tonic()
-                .with_endpoint(otlp_endpoint.clone())
-                .with_timeout(Duration::from_secs(3)),
-        )
-        .with_period(Duration::from_secs(5))
-        .with_resource(resource.clone())
-        .build()?;
-    
+
+    // Set up tracing with the configured exporter protocol. gRPC talks to the
+    // endpoint as-is (port 4317 by default); HTTP/protobuf posts to `/v1/traces`
+    // on the HTTP endpoint (port 4318 by default). The tonic and HTTP exporter
+    // builders are distinct types, so the pipeline is assembled in each branch.
+    let traces_protocol = otlp_protocol("OTEL_EXPORTER_OTLP_TRACES_PROTOCOL");
+    let trace_config = trace::config()
+        .with_sampler(trace_sampler())
+        .with_id_generator(RandomIdGenerator::default())
+        .with_resource(resource.clone());
+    let tracer = if traces_protocol == "http/protobuf" {
+        opentelemetry_otlp::new_pipeline()
+            .tracing()
+            .with_exporter(http_exporter(format!(
+                "{}/v1/traces",
+                otlp_http_endpoint.trim_end_matches('/')
+            )))
+            .with_trace_config(trace_config)
+            .install_batch(opentelemetry_sdk::runtime::Tokio)?
+    } else {
+        opentelemetry_otlp::new_pipeline()
+            .tracing()
+            .with_exporter(tonic_exporter(&otlp_endpoint)?)
+            .with_trace_config(trace_config)
+            .install_batch(opentelemetry_sdk::runtime::Tokio)?
+    };
+
+    // Set up metrics. Two delivery paths can coexist, selected via
+    // `OTEL_METRICS_EXPORTER` (comma-separated, default `otlp`): the OTLP push
+    // reader and/or a Prometheus pull reader scraped at `/metrics`. Both are
+    // attached to a single `SdkMeterProvider` so the instruments recorded in
+    // `metrics_middleware` are visible to whichever path is enabled.
+    let metrics_exporters = std::env::var("OTEL_METRICS_EXPORTER")
+        .unwrap_or_else(|_| "otlp".to_string());
+    let metrics_push = metrics_exporters.split(',').any(|e| e.trim() == "otlp");
+    let metrics_pull = metrics_exporters.split(',').any(|e| e.trim() == "prometheus");
+
+    let mut provider_builder = SdkMeterProvider::builder().with_resource(resource.clone());
+
+    if metrics_push {
+        let metrics_protocol = otlp_protocol("OTEL_EXPORTER_OTLP_METRICS_PROTOCOL");
+        let exporter = if metrics_protocol == "http/protobuf" {
+            http_exporter(format!(
+                "{}/v1/metrics",
+                otlp_http_endpoint.trim_end_matches('/')
+            ))
+                .with_timeout(Duration::from_secs(3))
+                .build_metrics_exporter(
+                    Box::new(DefaultAggregationSelector::new()),
+                    Box::new(DefaultTemporalitySelector::new()),
+                )?
+        } else {
+            tonic_exporter(&otlp_endpoint)?
+                .with_timeout(Duration::from_secs(3))
+                .build_metrics_exporter(
+                    Box::new(DefaultAggregationSelector::new()),
+                    Box::new(DefaultTemporalitySelector::new()),
+                )?
+        };
+        let reader = PeriodicReader::builder(exporter, opentelemetry_sdk::runtime::Tokio)
+            .with_interval(Duration::from_secs(5))
+            .build();
+        provider_builder = provider_builder.with_reader(reader);
+    }
+
+    // Prometheus pull reader: keep the registry so `/metrics` can encode it.
+    let prometheus_registry = if metrics_pull {
+        let registry = prometheus::Registry::new();
+        let exporter = opentelemetry_prometheus::exporter()
+            .with_registry(registry.clone())
+            .build()?;
+        provider_builder = provider_builder.with_reader(exporter);
+        Some(registry)
+    } else {
+        None
+    };
+
+    let meter_provider = provider_builder.build();
     global::set_meter_provider(meter_provider.clone());
-    
-    // Initialize tracing subscriber with OpenTelemetry layer
+
+    // Set up logs: export every tracing event as an OTLP log record correlated
+    // with the active trace/span via the appender bridge.
+    let logs_protocol = otlp_protocol("OTEL_EXPORTER_OTLP_LOGS_PROTOCOL");
+    let log_provider = logger_provider(&logs_protocol, &otlp_endpoint, &otlp_http_endpoint, resource.clone())?;
+    let otel_log_layer = OpenTelemetryTracingBridge::new(&log_provider);
+
+    // Optional tokio-console layer for live async runtime introspection,
+    // enabled at compile time with `--features tokio-console` (which also
+    // requires building with the unstable runtime hooks:
+    //     RUSTFLAGS="--cfg tokio_unstable" cargo build --features tokio-console
+    // ) and at runtime with `TOKIO_CONSOLE=1`. It serves task/poll/resource
+    // data on 0.0.0.0:6669 for the `tokio-console` client. The instrumentation
+    // it consumes is emitted on the `tokio`/`runtime` targets at trace level,
+    // so it is attached directly to the registry rather than behind the
+    // `RUST_LOG`-derived `EnvFilter` below, which would otherwise reject it
+    // under the default "info" level.
+    #[cfg(feature = "tokio-console")]
+    let console_layer = if std::env::var("TOKIO_CONSOLE").as_deref() == Ok("1") {
+        Some(
+            console_subscriber::ConsoleLayer::builder()
+                .with_default_env()
+                .server_addr(([0, 0, 0, 0], 6669))
+                .spawn(),
+        )
+    } else {
+        None
+    };
+    #[cfg(not(feature = "tokio-console"))]
+    let console_layer: Option<tracing_subscriber::layer::Identity> = None;
+
+    let env_filter = tracing_subscriber::EnvFilter::new(
+        std::env::var("RUST_LOG").unwrap_or_else(|_| "info".into()),
+    );
+
+    // Initialize tracing subscriber with OpenTelemetry layers. The EnvFilter
+    // is attached per-layer to fmt/OpenTelemetry only, so the console layer
+    // above still sees every event regardless of RUST_LOG.
     tracing_subscriber::registry()
-        .with(tracing_subscriber::EnvFilter::new(
-            std::env::var("RUST_LOG").unwrap_or_else(|_| "info".into()),
-        ))
-        .with(tracing_subscriber::fmt::layer())
-        .with(tracing_opentelemetry::layer().with_tracer(tracer))
+        .with(console_layer)
+        .with(tracing_subscriber::fmt::layer().with_filter(env_filter.clone()))
+        .with(tracing_opentelemetry::layer().with_tracer(tracer).with_filter(env_filter.clone()))
+        .with(otel_log_layer.with_filter(env_filter))
         .init();
-    
-    Ok(meter_provider)
+
+    Ok((meter_provider, log_provider, prometheus_registry))
 }
 
 #[tokio::main]
 async fn main() {
     // Initialize OpenTelemetry
-    let _meter_provider = init_telemetry()
+    let (_meter_provider, logger_provider, prometheus_registry) = init_telemetry()
         .expect("Failed to initialize OpenTelemetry");
+
+    // Expose the Prometheus registry to the scrape handler when enabled.
+    if let Some(registry) = prometheus_registry {
+        PROMETHEUS_REGISTRY.set(registry).expect("Failed to set Prometheus registry");
+    }
     
     info!("OpenTelemetry initialized successfully");
     
@@ -244,6 +508,7 @@ async fn main() {
         .route("/data", get(data_handler))
         .route("/error", get(error_handler))
         .route("/health", get(health_handler))
+        .route("/metrics", get(metrics_handler))
         .layer(middleware::from_fn(metrics_middleware))
         .layer(TraceLayer::new_for_http())
         .layer(cors);
@@ -261,4 +526,5 @@ async fn main() {
     
     // Shutdown OpenTelemetry
     global::shutdown_tracer_provider();
+    let _ = logger_provider.shutdown();
 }